@@ -2,7 +2,10 @@ use crate::error_template::{AppError, ErrorTemplate};
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
+use leptos_use::storage::{use_local_storage, JsonCodec};
+use leptos_use::use_event_listener;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[component]
 pub fn App() -> impl IntoView {
@@ -16,36 +19,66 @@ pub fn App() -> impl IntoView {
         <Title text="Soon Todo App"/>
 
         // content for this welcome page
-        <Router fallback=|| {
-            let mut outside_errors = Errors::default();
-            outside_errors.insert_with_default_key(AppError::NotFound);
-            view! { <ErrorTemplate outside_errors/> }.into_view()
-        }>
+        <Router fallback=not_found_view>
             <main>
                 <Routes>
-                    <Route path="" view=HomePage/>
+                    <Route path="/*any" view=HomePage/>
                 </Routes>
             </main>
         </Router>
     }
 }
 
+fn not_found_view() -> View {
+    let mut outside_errors = Errors::default();
+    outside_errors.insert_with_default_key(AppError::NotFound);
+    view! { <ErrorTemplate outside_errors/> }.into_view()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Bucket {
     Todo,
     Done,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    All,
+    Active,
+    Completed,
+}
+
+impl Filter {
+    // `None` means the path isn't one of ours (typo, garbage, etc.) and
+    // should fall through to the NotFound page rather than rendering the
+    // app, since `/*any` in `App`'s routes matches everything.
+    fn from_pathname(pathname: &str) -> Option<Self> {
+        match pathname {
+            "/" | "" => Some(Filter::All),
+            "/active" => Some(Filter::Active),
+            "/completed" => Some(Filter::Completed),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, bucket: Bucket) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Active => bucket == Bucket::Todo,
+            Filter::Completed => bucket == Bucket::Done,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Todo {
-    pub id: u32,
+    pub id: Uuid,
     pub text: String,
     pub bucket: Bucket,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct AppState {
-    pub uncommitted_todo: String,
     pub todos: Vec<Todo>,
 }
 
@@ -54,7 +87,6 @@ const STORAGE_KEY: &str = "app-state";
 impl Default for AppState {
     fn default() -> Self {
         AppState {
-            uncommitted_todo: String::new(),
             todos: Vec::<Todo>::new(),
         }
     }
@@ -62,88 +94,55 @@ impl Default for AppState {
 
 #[component]
 fn HomePage() -> impl IntoView {
-    let app_state = create_rw_signal(AppState::default());
-
-    let load_data = || {
-        let starting_todos = window().local_storage().ok().flatten().and_then(|storage| {
-            storage
-                .get_item(STORAGE_KEY)
-                .ok()
-                .flatten()
-                .and_then(|value| serde_json::from_str::<AppState>(&value).ok())
-        });
-
-        match starting_todos {
-            Some(todos) => {
-                logging::log!("starting_todos: {:?}", todos);
-                todos
-            }
-            None => AppState {
-                uncommitted_todo: String::new(),
-                todos: Vec::<Todo>::new(),
-            },
-        }
-    };
+    // Reactive signal already backed by `localStorage`. Todo mutations
+    // (add/toggle/delete/rename) are written through immediately so nothing
+    // is lost if the tab is closed right after. There's no Cargo.lock in
+    // this tree pinning a leptos-use version, so we don't rely on
+    // `listen_to_storage_changes` defaulting to on for cross-tab sync —
+    // the explicit listener below is what actually guarantees it.
+    let (app_state, set_app_state, _) = use_local_storage::<AppState, JsonCodec>(STORAGE_KEY);
 
-    let save_to_local_storage = move || {
+    // Pick up edits made to this key from other tabs/windows.
+    let _ = use_event_listener(window(), ev::storage, move |_| {
         if let Ok(Some(storage)) = window().local_storage() {
-            let state = AppState {
-                uncommitted_todo: app_state.get().uncommitted_todo.clone(),
-                todos: app_state.get().todos.clone(),
-            };
-            let json = serde_json::to_string(&state).expect("couldn't serialize Todos");
-            if storage.set_item(STORAGE_KEY, &json).is_err() {
-                logging::error!(
-                    "save_to_local_storage: error while trying to set item in localStorage"
-                );
+            if let Some(value) = storage.get_item(STORAGE_KEY).ok().flatten() {
+                if let Ok(synced) = serde_json::from_str::<AppState>(&value) {
+                    set_app_state.set(synced);
+                }
             }
         }
-    };
-
-    // Load data on first render
-    create_effect(move |_| {
-        app_state.set(load_data());
     });
 
-    // Save data on every change
-    let _ = watch(
-        move || app_state.get(),
-        move |new_todo_list, old_todo_list, _| {
-            logging::log!("new_todo_list: {:?}", new_todo_list);
-            logging::log!("old_todo_list: {:?}", old_todo_list);
-            save_to_local_storage();
-        },
-        false,
-    );
-
-    // TODO: Refactor everything below this line to use async_app_state_result
-    let (uncommitted_todo, set_uncommitted_todo) = create_slice(
-        app_state,
-        |state| state.uncommitted_todo.clone(),
-        |state, new_value: String| {
-            logging::log!("set_uncommitted_todo: {:?}", new_value);
-            state.uncommitted_todo = new_value.clone();
-        },
-    );
-
-    let todo_list = move || {
+    // Kept out of `AppState`: it's just in-progress input, not something that
+    // needs to survive a reload, and keeping it off the synced signal means
+    // keystrokes never touch localStorage at all.
+    let (uncommitted_todo, set_uncommitted_todo) = create_signal(String::new());
+
+    let location = use_location();
+    let filter = move || Filter::from_pathname(&location.pathname.get());
+
+    let filtered_todos = move || {
+        let filter = filter().unwrap_or(Filter::All);
         app_state
             .get()
             .todos
             .iter()
-            .filter(|todo| todo.bucket == Bucket::Todo)
+            .filter(|todo| filter.matches(todo.bucket))
             .cloned()
             .collect::<Vec<Todo>>()
     };
 
-    let done_list = move || {
+    let items_left = move || {
         app_state
             .get()
             .todos
             .iter()
-            .filter(|todo| todo.bucket == Bucket::Done)
-            .cloned()
-            .collect::<Vec<Todo>>()
+            .filter(|todo| todo.bucket == Bucket::Todo)
+            .count()
+    };
+
+    let clear_completed = move |_| {
+        set_app_state.update(|state| state.todos.retain(|todo| todo.bucket != Bucket::Done));
     };
 
     let input_element: NodeRef<html::Input> = create_node_ref();
@@ -151,34 +150,59 @@ fn HomePage() -> impl IntoView {
     let on_click = move |_| {
         let value = input_element().expect("<input> should be mounted").value();
 
-        app_state.update(move |state| {
+        set_app_state.update(move |state| {
             state.todos.push(Todo {
-                id: state.todos.len() as u32, // Simple ID generation
+                id: Uuid::new_v4(),
                 text: value.to_string(),
                 bucket: Bucket::Todo, // New todos start in the Soon bucket
             });
         });
     };
 
-    let mark_as_todo = move |index: u32| {
+    let mark_as_todo = move |id: Uuid| {
         logging::log!("move to done");
-        app_state.update(|state| {
-            if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == index) {
+        set_app_state.update(|state| {
+            if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == id) {
                 todo.bucket = Bucket::Todo;
             }
         });
     };
 
-    let mark_as_done = move |index: u32| {
+    let mark_as_done = move |id: Uuid| {
         logging::log!("move to done");
-        app_state.update(|state| {
-            if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == index) {
+        set_app_state.update(|state| {
+            if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == id) {
                 todo.bucket = Bucket::Done;
             }
         });
     };
 
+    let delete_todo = move |id: Uuid| {
+        logging::log!("delete todo");
+        set_app_state.update(|state| {
+            state.todos.retain(|todo| todo.id != id);
+        });
+    };
+
+    let (editing, set_editing) = create_signal(None::<Uuid>);
+
+    let rename_todo = move |id: Uuid, text: String| {
+        let trimmed = text.trim().to_string();
+        set_app_state.update(|state| {
+            if trimmed.is_empty() {
+                state.todos.retain(|todo| todo.id != id);
+            } else if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == id) {
+                todo.text = trimmed;
+            }
+        });
+        set_editing.set(None);
+    };
+
     view! {
+        // `/*any` above matches every path, so routes outside our three
+        // filters (typos, garbage) are rejected here rather than silently
+        // rendering the app.
+        <Show when=move || filter().is_some() fallback=not_found_view>
         <Style>r#"
           button { margin-left: 8px;}
           .done { color: gray; text-decoration: line-through;}
@@ -190,35 +214,88 @@ fn HomePage() -> impl IntoView {
             placeholder="Add todo"
             prop:value=uncommitted_todo
             on:input=move |ev| {
-                let new_value = event_target_value(&ev);
-                //logging::log!("wtf: {:?}", new_value);
-                set_uncommitted_todo.set(new_value);
+                set_uncommitted_todo.set(event_target_value(&ev));
             }
             node_ref=input_element
         />
         <button
         on:click=on_click
         >Add todo</button>
-        <h2>Todo List</h2>
+        <h2>Todos</h2>
         <Suspense fallback=move || view! { <p>"Loading..."</p> }>
         <div>
-        {move || todo_list().into_iter().map(|todo| view! {
-            <input
-                type="checkbox"
-                name="todo"
-                on:input=move |_| mark_as_done(todo.id)
-            /> {todo.text}
-            <br/>
+        {move || filtered_todos().into_iter().map(|todo| {
+            let done = todo.bucket == Bucket::Done;
+            let id = todo.id;
+            let text = todo.text.clone();
+            view! {
+                <Show
+                    when=move || editing.get() == Some(id)
+                    fallback=move || {
+                        let text = text.clone();
+                        view! {
+                            <input
+                                type="checkbox"
+                                name="todo"
+                                checked=done
+                                on:input=move |_| if done { mark_as_todo(id) } else { mark_as_done(id) }
+                            />
+                            <span class:done=done on:dblclick=move |_| set_editing.set(Some(id))>
+                                {text}
+                            </span>
+                            <button on:click=move |_| delete_todo(id)>Delete</button>
+                            <br/>
+                        }
+                    }
+                >
+                    {
+                        let text = todo.text.clone();
+                        let edit_input: NodeRef<html::Input> = create_node_ref();
+
+                        // Focus and select the text as soon as the edit input
+                        // mounts, matching TodoMVC's double-click-to-edit feel.
+                        create_effect(move |_| {
+                            if let Some(input) = edit_input.get() {
+                                let _ = input.focus();
+                                let _ = input.select();
+                            }
+                        });
+
+                        view! {
+                            <input
+                                type="text"
+                                prop:value=text
+                                node_ref=edit_input
+                                on:keydown=move |ev| {
+                                    // Commit happens in on:blur below; Enter just
+                                    // triggers that blur instead of committing twice.
+                                    if ev.key() == "Enter" {
+                                        if let Some(input) = edit_input.get() {
+                                            let _ = input.blur();
+                                        }
+                                    }
+                                }
+                                on:blur=move |ev| {
+                                    rename_todo(id, event_target_value(&ev));
+                                }
+                            />
+                            <br/>
+                        }
+                    }
+                </Show>
+            }
         }).collect_view()}
         </div>
-        <h2>Done List</h2>
-        <div>
-            {move || done_list().into_iter().map(|todo| view! {
-                <span class="done"> {todo.text} </span>
-                <button on:click=move |_| mark_as_todo(todo.id)>UNDO</button>
-                <br/>
-            }).collect_view()}
-        </div>
+        <footer>
+            <span>{items_left} " items left"</span>
+            <nav>
+                <A href="/">All</A>
+                <A href="/active">Active</A>
+                <A href="/completed">Completed</A>
+            </nav>
+            <button on:click=clear_completed>Clear completed</button>
+        </footer>
         </Suspense>
+        </Show>
     }
 }